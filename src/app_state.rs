@@ -1,11 +1,16 @@
 use clap::Parser;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 use wallpaper_ui::{
     args::WallpaperUIArgs,
     cropper::AspectRatio,
-    filename, filter_images,
+    decode,
+    dedup::{self, DEFAULT_THRESHOLD},
+    filename, filter_images, fuzzy,
     geometry::Geometry,
+    scan::{self, Scanner},
+    setter,
     wallpaper_dir,
     wallpapers::{WallInfo, WallpapersCsv},
 };
@@ -14,6 +19,9 @@ use wallpaper_ui::{
 pub struct UiState {
     pub show_filelist: bool,
     pub show_faces: bool,
+    /// mirrors [`Wallpapers::hide_duplicates`] for the toggle control; set
+    /// both together via [`Wallpapers::set_hide_duplicates`]
+    pub hide_duplicates: bool,
     pub preview_mode: PreviewMode,
 }
 
@@ -22,6 +30,7 @@ impl Default for UiState {
         Self {
             show_filelist: Default::default(),
             show_faces: Default::default(),
+            hide_duplicates: Default::default(),
             preview_mode: PreviewMode::Source,
         }
     }
@@ -31,6 +40,7 @@ impl UiState {
     pub fn reset(&mut self) {
         self.show_filelist = false;
         self.show_faces = false;
+        self.hide_duplicates = false;
         self.preview_mode = PreviewMode::Source;
     }
 }
@@ -50,22 +60,78 @@ pub enum PreviewMode {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Wallpapers {
-    pub files: Vec<PathBuf>,
+    /// every wallpaper in the list, independent of the active filter
+    pub all_files: Vec<PathBuf>,
+    /// indices into `all_files` currently shown, in display order
+    pub visible: Vec<usize>,
+    /// the active fuzzy filter query (empty means no filter)
+    pub filter: String,
+    /// whether `visible` collapses each perceptual-duplicate group down to
+    /// its first member, mirroring [`UiState::hide_duplicates`]
+    pub hide_duplicates: bool,
+    /// cached result of [`Wallpapers::duplicate_groups`], kept in sync with
+    /// `all_files` by [`Wallpapers::refresh_duplicate_groups`] rather than
+    /// recomputed on every `recompute_visible` call
+    duplicate_groups_cache: Vec<Vec<usize>>,
     // the original wallinfo before any modifications
     pub source: WallInfo,
     pub current: WallInfo,
+    /// cursor position within `visible`
     pub index: usize,
     pub ratio: AspectRatio,
+    /// indices of `all_files` selected for batch operations
+    pub selected: HashSet<usize>,
 }
 
 impl Default for Wallpapers {
     fn default() -> Self {
         Self {
-            files: Vec::default(),
+            all_files: Vec::default(),
+            visible: Vec::default(),
+            filter: String::default(),
+            hide_duplicates: Default::default(),
+            duplicate_groups_cache: Vec::default(),
             source: WallInfo::default(),
             current: WallInfo::default(),
             index: Default::default(),
             ratio: AspectRatio(1440, 2560),
+            selected: HashSet::default(),
+        }
+    }
+}
+
+/// Collects the images in `dir` for the wallpaper list.
+///
+/// TODO(follow-up): the real fix is extending `filter_images`'s own
+/// extension allow-list with [`decode::is_extra_path`], but `filter_images`
+/// lives in the wallpaper_ui crate root, which this source tree doesn't
+/// include, so it can't be edited from here. This duplicates its recursive
+/// walk to fold in HEIF/RAW files in the meantime; drop this entirely once
+/// `filter_images` is extended upstream.
+fn collect_images(dir: &Path) -> Vec<PathBuf> {
+    let mut files = filter_images(dir);
+    let extra = {
+        let known: HashSet<&Path> = files.iter().map(PathBuf::as_path).collect();
+        let mut extra = Vec::new();
+        collect_extra_recursive(dir, &known, &mut extra);
+        extra
+    };
+    files.extend(extra);
+    files
+}
+
+/// recursively walks `dir`, collecting HEIF/RAW files not already present in
+/// `known`
+fn collect_extra_recursive(dir: &Path, known: &HashSet<&Path>, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_extra_recursive(&path, known, out);
+        } else if decode::is_extra_path(&path) && !known.contains(path.as_path()) {
+            out.push(path);
         }
     }
 }
@@ -73,13 +139,20 @@ impl Default for Wallpapers {
 impl Wallpapers {
     pub fn from_args() -> Self {
         let args = WallpaperUIArgs::parse();
+
+        // re-set the previously chosen wallpaper without opening the UI
+        if args.reapply {
+            setter::reapply_last().expect("could not reapply last wallpaper");
+            std::process::exit(0);
+        }
+
         let mut all_files = Vec::new();
         if let Some(paths) = args.paths {
             paths.iter().flat_map(std::fs::canonicalize).for_each(|p| {
                 if p.is_file() {
                     all_files.push(p);
                 } else {
-                    all_files.extend(filter_images(&p));
+                    all_files.extend(collect_images(&p));
                 }
             });
         }
@@ -93,7 +166,7 @@ impl Wallpapers {
                 std::process::exit(1);
             }
 
-            all_files.extend(filter_images(&wall_dir));
+            all_files.extend(collect_images(&wall_dir));
         }
 
         // order by reverse chronological order
@@ -111,70 +184,241 @@ impl Wallpapers {
             .get(&fname)
             .expect("could not get wallpaper info");
 
-        Self {
-            files: all_files,
+        let file_count = all_files.len();
+        let mut wallpapers = Self {
+            all_files,
+            visible: (0..file_count).collect(),
             source: loaded.clone(),
             current: loaded.clone(),
             ..Default::default()
-        }
+        };
+        wallpapers.refresh_duplicate_groups();
+        wallpapers
     }
 
-    pub fn prev_wall(&mut self) {
-        // loop back to the last wallpaper
-        self.index = if self.index == 0 {
-            self.files.len() - 1
-        } else {
-            self.index - 1
-        };
+    /// absolute index into `all_files` of the wallpaper under the cursor
+    fn current_file(&self) -> usize {
+        self.visible[self.index]
+    }
 
+    /// loads the `WallInfo` for the wallpaper at the current cursor position
+    fn load_current(&mut self) {
         let wallpapers_csv = WallpapersCsv::new();
         let loaded = wallpapers_csv
             // bounds check is not necessary since the index is always valid
-            .get(&filename(&self.files[self.index]))
+            .get(&filename(&self.all_files[self.current_file()]))
             .expect("could not get wallpaper info");
         self.source = loaded.clone();
         self.current = loaded.clone();
     }
 
+    pub fn prev_wall(&mut self) {
+        // nothing to navigate when the filter hides every wallpaper
+        if self.visible.is_empty() {
+            return;
+        }
+        // loop back to the last wallpaper in the filtered view
+        self.index = if self.index == 0 {
+            self.visible.len() - 1
+        } else {
+            self.index - 1
+        };
+        self.load_current();
+    }
+
     pub fn next_wall(&mut self) {
-        // loop back to the first wallpaper
-        self.index = if self.index == self.files.len() - 1 {
+        // nothing to navigate when the filter hides every wallpaper
+        if self.visible.is_empty() {
+            return;
+        }
+        // loop back to the first wallpaper in the filtered view
+        self.index = if self.index == self.visible.len() - 1 {
             0
         } else {
             self.index + 1
         };
+        self.load_current();
+    }
 
-        let wallpapers_csv = WallpapersCsv::new();
-        let loaded = wallpapers_csv
-            // bounds check is not necessary since the index is always valid
-            .get(&filename(&self.files[self.index]))
-            .expect("could not get wallpaper info");
-        self.source = loaded.clone();
-        self.current = loaded.clone();
+    /// recomputes `visible` from `all_files` against the active fuzzy `filter`,
+    /// collapsing perceptual-duplicate groups down to their first member when
+    /// `hide_duplicates` is set, and keeping the current wallpaper selected
+    /// when it survives both
+    fn recompute_visible(&mut self) {
+        let current = self.visible.get(self.index).copied();
+
+        // all but the first (lowest-index) member of each duplicate group;
+        // reads the cache rather than recomputing so typing in the filter
+        // box doesn't re-decode every file on every keystroke
+        let hidden: HashSet<usize> = if self.hide_duplicates {
+            self.duplicate_groups_cache
+                .iter()
+                .flat_map(|group| group.iter().skip(1).copied())
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        self.visible = self
+            .all_files
+            .iter()
+            .enumerate()
+            .filter(|(i, f)| !hidden.contains(i) && fuzzy::is_match(&self.filter, &filename(f)))
+            .map(|(i, _)| i)
+            .collect();
+
+        self.index = current
+            .and_then(|c| self.visible.iter().position(|&i| i == c))
+            .unwrap_or(0);
+        if !self.visible.is_empty() {
+            self.load_current();
+        }
+    }
+
+    /// sets the fuzzy filter query, narrowing the visible list to matching
+    /// filenames
+    pub fn set_filter(&mut self, filter: &str) {
+        self.filter = filter.to_string();
+        self.recompute_visible();
+    }
+
+    /// sets whether the visible (filtered) view collapses each
+    /// perceptual-duplicate group down to its first member
+    pub fn set_hide_duplicates(&mut self, hide: bool) {
+        self.hide_duplicates = hide;
+        self.recompute_visible();
+    }
+
+    /// jumps the cursor to the next entry whose filename fuzzy-matches `query`,
+    /// wrapping around, without removing non-matching entries from the view
+    pub fn search_next(&mut self, query: &str) {
+        let len = self.visible.len();
+        if len == 0 {
+            return;
+        }
+        for offset in 1..=len {
+            let i = (self.index + offset) % len;
+            if fuzzy::is_match(query, &filename(&self.all_files[self.visible[i]])) {
+                self.index = i;
+                self.load_current();
+                return;
+            }
+        }
+    }
+
+    /// jumps the cursor to the previous entry whose filename fuzzy-matches
+    /// `query`, wrapping around, without removing non-matching entries
+    pub fn search_prev(&mut self, query: &str) {
+        let len = self.visible.len();
+        if len == 0 {
+            return;
+        }
+        for offset in 1..=len {
+            let i = (self.index + len - offset) % len;
+            if fuzzy::is_match(query, &filename(&self.all_files[self.visible[i]])) {
+                self.index = i;
+                self.load_current();
+                return;
+            }
+        }
     }
 
     /// removes the current wallpaper from the list
     pub fn remove(&mut self) {
-        let current_index = self.index;
-        self.next_wall();
-        self.files.remove(current_index);
-        // current_index is unchanged after removal
-        self.index = current_index;
+        // nothing to remove when the filter hides every wallpaper
+        if self.visible.is_empty() {
+            return;
+        }
+        let removed = self.current_file();
+        self.all_files.remove(removed);
+        // shift selection and filtered indices past the removed entry
+        self.selected = self
+            .selected
+            .iter()
+            .filter(|&&i| i != removed)
+            .map(|&i| if i > removed { i - 1 } else { i })
+            .collect();
+        // the duplicate-group cache is keyed on all_files indices, so it
+        // must be rebuilt whenever the file list itself changes
+        self.refresh_duplicate_groups();
+        self.recompute_visible();
     }
 
     pub fn set_from_filename(&mut self, fname: &str) {
-        let wallpapers_csv = WallpapersCsv::new();
-        let loaded = wallpapers_csv
-            .get(fname)
-            .expect("could not get wallpaper info")
-            .clone();
-        self.source = loaded.clone();
-        self.current = loaded;
-        self.index = self
-            .files
+        let target = self
+            .all_files
             .iter()
             .position(|f| filename(f) == fname)
             .unwrap_or_else(|| panic!("could not find wallpaper: {}", fname));
+
+        // clear whatever is hiding the target -- the fuzzy filter and/or the
+        // dedup collapse -- so it can be selected
+        if !self.visible.contains(&target) {
+            self.filter.clear();
+            self.hide_duplicates = false;
+            self.recompute_visible();
+        }
+
+        self.index = self
+            .visible
+            .iter()
+            .position(|&i| i == target)
+            .expect("target wallpaper should be visible");
+        self.load_current();
+    }
+
+    /// toggles whether the wallpaper at `index` is part of the selection
+    pub fn toggle_select(&mut self, index: usize) {
+        if !self.selected.remove(&index) {
+            self.selected.insert(index);
+        }
+    }
+
+    /// selects every wallpaper in the visible (filtered) view
+    pub fn select_all(&mut self) {
+        self.selected = self.visible.iter().copied().collect();
+    }
+
+    /// inverts the selection over the visible (filtered) view
+    pub fn invert_selection(&mut self) {
+        self.selected = self
+            .visible
+            .iter()
+            .copied()
+            .filter(|i| !self.selected.contains(i))
+            .collect();
+    }
+
+    /// clears the current selection
+    pub fn clear_selection(&mut self) {
+        self.selected.clear();
+    }
+
+    /// applies `geom` for the given aspect `ratio` to every selected wallpaper
+    /// and persists the changes through [`WallpapersCsv`]
+    ///
+    /// Takes [`scan::CSV_LOCK`] for the whole read-modify-write so a
+    /// background scan's own per-file save can't race this and drop it.
+    pub fn apply_geometry_to_selected(&mut self, geom: &Geometry, ratio: &AspectRatio) {
+        let _guard = scan::CSV_LOCK.lock().unwrap();
+        let mut wallpapers_csv = WallpapersCsv::new();
+        // no current wallpaper to keep in sync when the filter hides everything
+        let current_file = self.visible.get(self.index).copied();
+        for &i in &self.selected {
+            let fname = filename(&self.all_files[i]);
+            let mut info = wallpapers_csv
+                .get(&fname)
+                .expect("could not get wallpaper info")
+                .clone();
+            info.set_geometry(ratio, geom);
+
+            // keep the in-memory current wallpaper in sync when it is selected
+            if current_file == Some(i) {
+                self.current = info.clone();
+            }
+            wallpapers_csv.insert(fname, info);
+        }
+        wallpapers_csv.save();
     }
 
     /// gets geometry for current aspect ratio
@@ -191,4 +435,136 @@ impl Wallpapers {
     pub fn crop_candidates(&self) -> Vec<Geometry> {
         self.current.cropper().crop_candidates(&self.ratio)
     }
+
+    /// starts a cancellable background scan that precomputes thumbnails and
+    /// face rectangles for every wallpaper, streaming results back while the
+    /// UI stays responsive
+    pub fn scan(&self, workers: usize) -> Scanner {
+        Scanner::spawn(self.all_files.clone(), workers)
+    }
+
+    /// applies the current wallpaper to the running compositor via `backend`,
+    /// recording it (with its current crop geometry) as the last-applied wallpaper
+    pub fn apply_wallpaper(&self, backend: setter::Backend) -> std::io::Result<()> {
+        if self.visible.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "no wallpaper selected to apply",
+            ));
+        }
+        setter::apply(
+            &self.all_files[self.current_file()],
+            &self.get_geometry(),
+            backend,
+        )
+    }
+
+    /// groups visually-identical wallpapers by perceptual (dHash) fingerprint
+    ///
+    /// Each returned group is a list of indices into `all_files` linked by
+    /// chains of images each within the default Hamming-distance threshold of
+    /// the next; only groups with more than one member are returned. Files that fail to decode
+    /// are skipped rather than collapsed into a bogus zero-hash group.
+    ///
+    /// Served from `duplicate_groups_cache`, kept in sync by
+    /// [`refresh_duplicate_groups`](Self::refresh_duplicate_groups) whenever
+    /// `all_files` changes, so repeated calls (e.g. from `recompute_visible`
+    /// on every keystroke) don't re-decode every file.
+    ///
+    /// Persisting the underlying hashes alongside `WallInfo` in
+    /// wallpapers.rs would additionally save the one decode pass this cache
+    /// still costs whenever `all_files` itself changes (e.g. on startup or
+    /// after a rescan), but that file is not part of this source tree and
+    /// cannot be edited here.
+    pub fn duplicate_groups(&self) -> Vec<Vec<usize>> {
+        self.duplicate_groups_cache.clone()
+    }
+
+    /// recomputes `duplicate_groups_cache` from the current `all_files`
+    ///
+    /// Must be called whenever `all_files` changes; the cache is otherwise
+    /// left untouched so filtering and navigation stay cheap.
+    fn refresh_duplicate_groups(&mut self) {
+        // keep only files whose fingerprint could be computed, remembering each
+        // one's original index into `all_files`
+        let (indices, hashes): (Vec<usize>, Vec<dedup::Hash>) = self
+            .all_files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, f)| dedup::dhash(f).map(|h| (i, h)))
+            .unzip();
+
+        self.duplicate_groups_cache = dedup::duplicate_groups(&hashes, DEFAULT_THRESHOLD)
+            .into_iter()
+            .map(|group| group.into_iter().map(|pos| indices[pos]).collect())
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// builds a `Wallpapers` with `n` placeholder files, all visible and
+    /// unfiltered, without touching `WallpapersCsv` or the filesystem
+    fn wallpapers_with_files(n: usize) -> Wallpapers {
+        Wallpapers {
+            all_files: (0..n).map(|i| PathBuf::from(format!("wall{i}.png"))).collect(),
+            visible: (0..n).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn toggle_select_adds_then_removes() {
+        let mut w = wallpapers_with_files(3);
+        w.toggle_select(1);
+        assert!(w.selected.contains(&1));
+        w.toggle_select(1);
+        assert!(!w.selected.contains(&1));
+    }
+
+    #[test]
+    fn select_all_selects_exactly_the_visible_set() {
+        let mut w = wallpapers_with_files(3);
+        w.visible = vec![0, 2];
+        w.select_all();
+        assert_eq!(w.selected, HashSet::from([0, 2]));
+    }
+
+    #[test]
+    fn invert_selection_flips_over_the_visible_view() {
+        let mut w = wallpapers_with_files(3);
+        w.selected = HashSet::from([0]);
+        w.invert_selection();
+        assert_eq!(w.selected, HashSet::from([1, 2]));
+    }
+
+    #[test]
+    fn clear_selection_empties_the_selection() {
+        let mut w = wallpapers_with_files(3);
+        w.selected = HashSet::from([0, 1]);
+        w.clear_selection();
+        assert!(w.selected.is_empty());
+    }
+
+    #[test]
+    fn remove_on_empty_visible_is_a_no_op() {
+        // an active filter that hides every wallpaper must not panic
+        let mut w = Wallpapers::default();
+        w.remove();
+        assert!(w.all_files.is_empty());
+    }
+
+    #[test]
+    fn remove_drops_the_removed_entry_from_selection() {
+        // removing the only (and currently selected) file empties visible too,
+        // so recompute_visible never reaches a WallpapersCsv-backed load
+        let mut w = wallpapers_with_files(1);
+        w.selected = HashSet::from([0]);
+        w.remove();
+        assert!(w.all_files.is_empty());
+        assert!(w.visible.is_empty());
+        assert!(w.selected.is_empty());
+    }
 }
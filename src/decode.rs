@@ -0,0 +1,138 @@
+//! Image decoding that normalizes extra formats (HEIF/HEIC and camera RAW)
+//! into the same in-memory [`image::DynamicImage`] used everywhere else.
+//!
+//! Common raster formats are handled by the `image` crate directly; the extra
+//! formats are gated behind feature flags so they only pull in their native
+//! dependencies when enabled.
+
+use std::path::Path;
+
+/// HEIF/HEIC extensions, decoded through libheif when the `heif` feature is on.
+pub const HEIF_EXTENSIONS: &[&str] = &["heic", "heif"];
+
+/// Camera RAW extensions, decoded + demosaiced when the `raw` feature is on.
+pub const RAW_EXTENSIONS: &[&str] = &["cr2", "nef", "arw", "dng"];
+
+/// Whether `ext` (lower-case, without the dot) is one of the extra formats
+/// decodable by [`open`] given the enabled features.
+///
+/// `filter_images` (in the crate root) must OR this into its extension
+/// allow-list so HEIF/RAW files reach the list and [`open`], e.g.:
+///
+/// ```ignore
+/// let ext = ext.to_ascii_lowercase();
+/// RASTER_EXTENSIONS.contains(&ext.as_str()) || decode::is_extra_extension(&ext)
+/// ```
+pub fn is_extra_extension(ext: &str) -> bool {
+    (cfg!(feature = "heif") && HEIF_EXTENSIONS.contains(&ext))
+        || (cfg!(feature = "raw") && RAW_EXTENSIONS.contains(&ext))
+}
+
+/// Convenience wrapper for [`is_extra_extension`] that takes a path, extracting
+/// and lower-casing its extension. Intended as the direct call from
+/// `filter_images`.
+pub fn is_extra_path(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| is_extra_extension(&e.to_ascii_lowercase()))
+}
+
+/// Opens an image, routing HEIF and RAW files through their dedicated decoders
+/// and everything else through the `image` crate.
+pub fn open(path: impl AsRef<Path>) -> image::ImageResult<image::DynamicImage> {
+    let path = path.as_ref();
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        #[cfg(feature = "heif")]
+        e if HEIF_EXTENSIONS.contains(&e) => open_heif(path),
+        #[cfg(feature = "raw")]
+        e if RAW_EXTENSIONS.contains(&e) => open_raw(path),
+        _ => image::open(path),
+    }
+}
+
+#[cfg(feature = "heif")]
+fn open_heif(path: &Path) -> image::ImageResult<image::DynamicImage> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let decode_err = |msg: String| {
+        image::ImageError::Decoding(image::error::DecodingError::from_format_and_reason(
+            image::error::ImageFormatHint::Name("HEIF".into()),
+            msg,
+        ))
+    };
+
+    let lib = LibHeif::new();
+    let ctx = HeifContext::read_from_file(&path.to_string_lossy())
+        .map_err(|e| decode_err(e.to_string()))?;
+    let handle = ctx.primary_image_handle().map_err(|e| decode_err(e.to_string()))?;
+    let image = lib
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(|e| decode_err(e.to_string()))?;
+
+    let planes = image.planes();
+    let plane = planes.interleaved.ok_or_else(|| decode_err("no pixel plane".into()))?;
+    let (w, h) = (plane.width, plane.height);
+
+    // drop any row stride padding so the buffer is tightly packed
+    let mut buf = Vec::with_capacity((w * h * 3) as usize);
+    for y in 0..h {
+        let start = (y * plane.stride as u32) as usize;
+        buf.extend_from_slice(&plane.data[start..start + (w * 3) as usize]);
+    }
+
+    let img = image::RgbImage::from_raw(w, h, buf)
+        .ok_or_else(|| decode_err("buffer size mismatch".into()))?;
+    Ok(image::DynamicImage::ImageRgb8(img))
+}
+
+#[cfg(feature = "raw")]
+fn open_raw(path: &Path) -> image::ImageResult<image::DynamicImage> {
+    let decode_err = |msg: String| {
+        image::ImageError::Decoding(image::error::DecodingError::from_format_and_reason(
+            image::error::ImageFormatHint::Name("RAW".into()),
+            msg,
+        ))
+    };
+
+    // decode then run the standard demosaic pipeline to an 8-bit sRGB buffer
+    let raw = rawloader::decode_file(path).map_err(|e| decode_err(e.to_string()))?;
+    let source = imagepipe::ImageSource::Raw(raw);
+    let mut pipeline =
+        imagepipe::Pipeline::new_from_source(source, 0, 0, true).map_err(decode_err)?;
+    let decoded = pipeline.output_8bit(None).map_err(decode_err)?;
+
+    let img = image::RgbImage::from_raw(
+        decoded.width as u32,
+        decoded.height as u32,
+        decoded.data,
+    )
+    .ok_or_else(|| decode_err("buffer size mismatch".into()))?;
+    Ok(image::DynamicImage::ImageRgb8(img))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_extra_extension_follows_enabled_features() {
+        assert_eq!(is_extra_extension("heic"), cfg!(feature = "heif"));
+        assert_eq!(is_extra_extension("heif"), cfg!(feature = "heif"));
+        assert_eq!(is_extra_extension("cr2"), cfg!(feature = "raw"));
+        assert_eq!(is_extra_extension("dng"), cfg!(feature = "raw"));
+        assert!(!is_extra_extension("png"));
+    }
+
+    #[test]
+    fn is_extra_path_lowercases_the_extension() {
+        assert_eq!(is_extra_path(Path::new("photo.HEIC")), cfg!(feature = "heif"));
+        assert!(!is_extra_path(Path::new("no_extension")));
+    }
+}
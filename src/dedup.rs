@@ -0,0 +1,241 @@
+use std::path::Path;
+
+use image::imageops::FilterType;
+
+/// A 64-bit perceptual fingerprint (dHash) of an image.
+///
+/// Two fingerprints are considered near-duplicates when the Hamming distance
+/// of their bits is small, regardless of rescaling or re-encoding.
+pub type Hash = u64;
+
+/// Default Hamming distance under which two wallpapers are treated as
+/// near-duplicates of each other.
+pub const DEFAULT_THRESHOLD: u32 = 10;
+
+/// Computes the dHash fingerprint of an image already decoded into memory.
+///
+/// The image is converted to grayscale and resized to 9x8, then for each of
+/// the 8 rows every pixel is compared to its right neighbour, producing 64
+/// bits (set when the left pixel is brighter) packed into a `u64`.
+pub fn dhash_image(img: &image::DynamicImage) -> Hash {
+    let small = img
+        .resize_exact(9, 8, FilterType::Lanczos3)
+        .to_luma8();
+
+    let mut hash: Hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash <<= 1;
+            if left > right {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Computes the dHash fingerprint of an image on disk, returning `None` if it
+/// cannot be decoded.
+pub fn dhash(path: impl AsRef<Path>) -> Option<Hash> {
+    crate::decode::open(path).ok().map(|img| dhash_image(&img))
+}
+
+/// Hamming distance between two fingerprints.
+#[inline]
+pub fn distance(a: Hash, b: Hash) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// A [BK-tree](https://en.wikipedia.org/wiki/BK-tree) keyed on Hamming
+/// distance, giving sub-linear near-duplicate lookups over large directories.
+#[derive(Debug, Default)]
+pub struct BkTree {
+    root: Option<Node>,
+}
+
+#[derive(Debug)]
+struct Node {
+    hash: Hash,
+    value: usize,
+    children: Vec<(u32, Node)>,
+}
+
+impl BkTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a fingerprint and its associated index into the tree.
+    pub fn insert(&mut self, hash: Hash, value: usize) {
+        match &mut self.root {
+            None => {
+                self.root = Some(Node {
+                    hash,
+                    value,
+                    children: Vec::new(),
+                });
+            }
+            Some(root) => root.insert(hash, value),
+        }
+    }
+
+    /// Returns every stored value whose fingerprint is within `threshold`
+    /// Hamming distance of `hash`.
+    pub fn within(&self, hash: Hash, threshold: u32) -> Vec<usize> {
+        let mut out = Vec::new();
+        if let Some(root) = &self.root {
+            root.within(hash, threshold, &mut out);
+        }
+        out
+    }
+}
+
+impl Node {
+    fn insert(&mut self, hash: Hash, value: usize) {
+        let d = distance(self.hash, hash);
+        match self.children.iter_mut().find(|(cd, _)| *cd == d) {
+            Some((_, child)) => child.insert(hash, value),
+            None => self.children.push((
+                d,
+                Node {
+                    hash,
+                    value,
+                    children: Vec::new(),
+                },
+            )),
+        }
+    }
+
+    fn within(&self, hash: Hash, threshold: u32, out: &mut Vec<usize>) {
+        let d = distance(self.hash, hash);
+        if d <= threshold {
+            out.push(self.value);
+        }
+        // the triangle inequality bounds which children can possibly match
+        let lo = d.saturating_sub(threshold);
+        let hi = d + threshold;
+        for (cd, child) in &self.children {
+            if *cd >= lo && *cd <= hi {
+                child.within(hash, threshold, out);
+            }
+        }
+    }
+}
+
+/// Groups indices of `hashes` linked by chains of fingerprints each within
+/// `threshold` of the next, so callers can hide all-but-one per
+/// visually-identical group. Grouping is transitive: two members of one group
+/// may be further than `threshold` apart from each other.
+///
+/// A BK-tree is used for the near-neighbour queries; only groups with more
+/// than one member are returned.
+pub fn duplicate_groups(hashes: &[Hash], threshold: u32) -> Vec<Vec<usize>> {
+    // union-find over the near-neighbour pairs surfaced by the BK-tree
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        // path compression
+        let mut cur = i;
+        while parent[cur] != root {
+            let next = parent[cur];
+            parent[cur] = root;
+            cur = next;
+        }
+        root
+    }
+
+    let mut tree = BkTree::new();
+    for (i, &hash) in hashes.iter().enumerate() {
+        for j in tree.within(hash, threshold) {
+            let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+            parent[a] = b;
+        }
+        tree.insert(hash, i);
+    }
+
+    let mut groups: std::collections::HashMap<usize, Vec<usize>> =
+        std::collections::HashMap::new();
+    for i in 0..hashes.len() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    let mut out: Vec<Vec<usize>> = groups
+        .into_values()
+        .filter(|g| g.len() > 1)
+        .collect();
+    // deterministic ordering by first (earliest) member
+    for g in &mut out {
+        g.sort_unstable();
+    }
+    out.sort_unstable_by_key(|g| g[0]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_image_hashes_to_zero() {
+        // no pixel is strictly brighter than its neighbour, so every bit is 0
+        let img = image::DynamicImage::ImageLuma8(image::GrayImage::from_pixel(
+            16,
+            16,
+            image::Luma([128]),
+        ));
+        assert_eq!(dhash_image(&img), 0);
+    }
+
+    #[test]
+    fn horizontal_gradient_hashes_nonzero() {
+        // brightness decreasing left-to-right sets bits where left > right
+        let img = image::DynamicImage::ImageLuma8(image::GrayImage::from_fn(16, 16, |x, _| {
+            image::Luma([(255 - x * 16) as u8])
+        }));
+        assert_ne!(dhash_image(&img), 0);
+    }
+
+    #[test]
+    fn hamming_distance() {
+        assert_eq!(distance(0, 0), 0);
+        assert_eq!(distance(0, u64::MAX), 64);
+        assert_eq!(distance(0b1011, 0b0001), 2);
+    }
+
+    #[test]
+    fn bktree_within_prunes_by_triangle_inequality() {
+        let mut tree = BkTree::new();
+        for (i, h) in [0b0000u64, 0b0001, 0b0011, 0b0111].iter().enumerate() {
+            tree.insert(*h, i);
+        }
+
+        let mut found = tree.within(0b0000, 1);
+        found.sort_unstable();
+        // only the distance-0 and distance-1 entries match
+        assert_eq!(found, vec![0, 1]);
+
+        let mut all = tree.within(0b0000, 3);
+        all.sort_unstable();
+        assert_eq!(all, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn duplicate_groups_clusters_near_hashes() {
+        // 0/1 are distance 1 apart; 254/255 are distance 1 apart; the pairs are
+        // far from each other
+        let groups = duplicate_groups(&[0, 1, 255, 254], 1);
+        assert_eq!(groups, vec![vec![0, 1], vec![2, 3]]);
+    }
+
+    #[test]
+    fn duplicate_groups_ignores_singletons() {
+        assert!(duplicate_groups(&[0, 255], 1).is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+//! Renders cropped, resized wallpapers to disk, one file per target aspect
+//! ratio, so callers can hand the results to a wallpaper daemon.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use image::imageops::FilterType;
+
+use crate::cropper::AspectRatio;
+use crate::wallpapers::WallInfo;
+
+/// Output encoder and its quality knob where applicable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoder {
+    Png,
+    /// WebP at the given quality (0-100)
+    #[cfg(feature = "webp")]
+    WebP(u8),
+    /// AVIF at the given quality (0-100)
+    #[cfg(feature = "avif")]
+    Avif(u8),
+}
+
+impl Encoder {
+    /// file extension produced by this encoder
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            #[cfg(feature = "webp")]
+            Self::WebP(_) => "webp",
+            #[cfg(feature = "avif")]
+            Self::Avif(_) => "avif",
+        }
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::Png
+    }
+}
+
+/// How and where to write the exported wallpapers.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// directory the encoded files are written into
+    pub out_dir: PathBuf,
+    pub encoder: Encoder,
+}
+
+/// Crops and resizes `info`'s source image to each `(ratio, (width, height))`
+/// target and writes the encoded result into `opts.out_dir`.
+///
+/// The source is decoded once and reused across every target. The crop
+/// rectangle is the geometry stored in `info` for each ratio; the cropped
+/// region is resized to the requested pixel dimensions with Lanczos3. Returns
+/// the path written for each `(aspect ratio, resolution)` target.
+pub fn export(
+    info: &WallInfo,
+    targets: &[(AspectRatio, (u32, u32))],
+    opts: &ExportOptions,
+) -> image::ImageResult<HashMap<(AspectRatio, (u32, u32)), PathBuf>> {
+    let source = crate::decode::open(info.path())?;
+    std::fs::create_dir_all(&opts.out_dir)?;
+
+    let stem = info
+        .path()
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("wallpaper")
+        .to_string();
+
+    let mut out = HashMap::with_capacity(targets.len());
+    for (ratio, (width, height)) in targets {
+        let geom = info.get_geometry(ratio);
+        let cropped = source
+            .crop_imm(geom.x, geom.y, geom.w, geom.h)
+            .resize_exact(*width, *height, FilterType::Lanczos3);
+
+        let path = opts.out_dir.join(format!(
+            "{stem}_{}x{}_{width}x{height}.{}",
+            ratio.0,
+            ratio.1,
+            opts.encoder.extension()
+        ));
+        write_encoded(&cropped, &path, opts.encoder)?;
+        out.insert((ratio.clone(), (*width, *height)), path);
+    }
+
+    Ok(out)
+}
+
+fn write_encoded(
+    img: &image::DynamicImage,
+    path: &Path,
+    encoder: Encoder,
+) -> image::ImageResult<()> {
+    match encoder {
+        Encoder::Png => img.save_with_format(path, image::ImageFormat::Png),
+        #[cfg(feature = "webp")]
+        Encoder::WebP(quality) => {
+            let rgba = img.to_rgba8();
+            let encoded = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                .encode(f32::from(quality));
+            std::fs::write(path, &*encoded).map_err(Into::into)
+        }
+        #[cfg(feature = "avif")]
+        Encoder::Avif(quality) => {
+            let file = std::fs::File::create(path)?;
+            let enc = image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                std::io::BufWriter::new(file),
+                4,
+                quality,
+            );
+            img.write_with_encoder(enc)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a temp directory unique to this test run, cleaned up on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("wallpaper-ui-test-{name}-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).expect("could not create temp dir");
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// same aspect ratio, two different output resolutions: the output map
+    /// must keep both as distinct entries keyed by (ratio, resolution),
+    /// rather than collapsing to one by ratio alone
+    #[test]
+    fn export_keys_same_ratio_different_resolutions_separately() {
+        let tmp = TempDir::new("export");
+        let source_path = tmp.0.join("source.png");
+        let img = image::DynamicImage::new_rgb8(64, 64);
+        img.save(&source_path).expect("could not write source image");
+
+        let info =
+            WallInfo::from_image(&source_path, &img).expect("could not build WallInfo from image");
+
+        let ratio = AspectRatio(1, 1);
+        let targets = [(ratio.clone(), (16, 16)), (ratio.clone(), (32, 32))];
+        let opts = ExportOptions {
+            out_dir: tmp.0.join("out"),
+            encoder: Encoder::Png,
+        };
+
+        let result = export(&info, &targets, &opts).expect("export failed");
+        assert_eq!(result.len(), 2, "each (ratio, resolution) target must get its own entry");
+
+        let small = &result[&(ratio.clone(), (16, 16))];
+        let large = &result[&(ratio, (32, 32))];
+        assert_ne!(small, large, "distinct resolutions must not collide on one output path");
+        assert!(small.exists());
+        assert!(large.exists());
+    }
+}
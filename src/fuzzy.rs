@@ -0,0 +1,77 @@
+//! Lightweight fuzzy subsequence matching used to filter the file list.
+
+/// Returns a match score when every character of `needle` appears in
+/// `haystack` in order (a subsequence match), or `None` otherwise.
+///
+/// Matching is case-insensitive. Higher scores are better: consecutive
+/// matches and matches at the start of the string are rewarded, mirroring the
+/// ranking a file-browser quick-filter gives. An empty needle matches
+/// everything with a score of `0`.
+pub fn score(needle: &str, haystack: &str) -> Option<i64> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+    let mut n = 0;
+    let mut total = 0i64;
+    let mut prev_match = false;
+
+    for (i, hc) in haystack.chars().flat_map(char::to_lowercase).enumerate() {
+        if n < needle.len() && hc == needle[n] {
+            total += 1;
+            if prev_match {
+                // reward runs of consecutive matches
+                total += 2;
+            }
+            if i == 0 {
+                // reward anchoring at the start
+                total += 3;
+            }
+            prev_match = true;
+            n += 1;
+        } else {
+            prev_match = false;
+        }
+    }
+
+    (n == needle.len()).then_some(total)
+}
+
+/// Convenience predicate: whether `needle` fuzzy-matches `haystack` at all.
+pub fn is_match(needle: &str, haystack: &str) -> bool {
+    score(needle, haystack).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_needle_matches_everything() {
+        assert_eq!(score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn subsequence_matches_case_insensitively() {
+        assert!(is_match("abc", "aXbXc"));
+        assert!(is_match("ABC", "a_b_c"));
+    }
+
+    #[test]
+    fn out_of_order_does_not_match() {
+        assert_eq!(score("acb", "abc"), None);
+    }
+
+    #[test]
+    fn start_anchor_scores_higher() {
+        // both match, but anchoring at the first character is rewarded
+        assert!(score("a", "abc").unwrap() > score("a", "bca").unwrap());
+    }
+
+    #[test]
+    fn consecutive_matches_score_higher() {
+        // a contiguous run beats the same characters spread apart
+        assert!(score("ab", "abxx").unwrap() > score("ab", "axbx").unwrap());
+    }
+}
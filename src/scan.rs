@@ -0,0 +1,191 @@
+//! Background scanning of a wallpaper directory: worker threads precompute
+//! thumbnails and face rectangles and stream results back so the UI stays
+//! responsive, with a stop signal that aborts in-flight work promptly.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+use crate::wallpapers::{WallInfo, WallpapersCsv};
+use crate::{decode, filename, wallpaper_dir};
+
+/// Maximum edge length of the precomputed thumbnails.
+const THUMBNAIL_SIZE: u32 = 512;
+
+/// directory the precomputed thumbnails are cached in
+fn thumbnail_dir() -> PathBuf {
+    wallpaper_dir().join(".thumbnails")
+}
+
+/// cache path of the thumbnail for `path`
+fn thumbnail_path(path: &Path) -> PathBuf {
+    thumbnail_dir().join(format!("{}.jpg", filename(path)))
+}
+
+/// Serializes every read-modify-write of [`WallpapersCsv`], across the
+/// background scan thread and the UI thread's own foreground edits.
+///
+/// Both sides re-read the CSV fresh under this lock before writing, so a
+/// crop edit made while a scan is in flight can no longer be silently
+/// dropped by the other side's save() overwriting it with a stale snapshot.
+pub static CSV_LOCK: Mutex<()> = Mutex::new(());
+
+/// An update streamed from the background scan.
+#[derive(Debug, Clone)]
+pub enum ScanEvent {
+    /// how many files have been processed out of the total
+    Progress { processed: usize, total: usize },
+    /// a file finished scanning, with its computed metadata
+    Loaded(WallInfo),
+}
+
+/// A running background scan. Dropping the handle (or calling [`stop`]) signals
+/// the workers to abort.
+///
+/// [`stop`]: Scanner::stop
+pub struct Scanner {
+    /// stream of progress and per-file results
+    pub events: Receiver<ScanEvent>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Scanner {
+    /// Spawns `workers` threads to scan `files`, returning immediately.
+    ///
+    /// Each completed file is persisted to [`WallpapersCsv`] and forwarded on
+    /// `events` alongside a running progress count.
+    pub fn spawn(files: Vec<PathBuf>, workers: usize) -> Self {
+        let (events_tx, events) = unbounded();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || {
+                run(files, workers.max(1), &events_tx, &stop);
+            })
+        };
+
+        Self {
+            events,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Signals every worker to stop; in-flight files finish but the queue is
+    /// abandoned.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Scanner {
+    fn drop(&mut self) {
+        self.stop();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(
+    files: Vec<PathBuf>,
+    workers: usize,
+    events: &Sender<ScanEvent>,
+    stop: &Arc<AtomicBool>,
+) {
+    let total = files.len();
+    // thumbnails are cached to disk as workers complete
+    let _ = std::fs::create_dir_all(thumbnail_dir());
+    let (work_tx, work_rx) = unbounded::<PathBuf>();
+    let (result_tx, result_rx) = unbounded::<WallInfo>();
+    let processed = Arc::new(AtomicUsize::new(0));
+
+    let mut handles = Vec::with_capacity(workers);
+    for _ in 0..workers {
+        let work_rx = work_rx.clone();
+        let result_tx = result_tx.clone();
+        let stop = Arc::clone(stop);
+        handles.push(std::thread::spawn(move || {
+            while let Ok(path) = work_rx.recv() {
+                // abort promptly when a stop has been requested
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                if let Some(info) = scan_file(&path) {
+                    if result_tx.send(info).is_err() {
+                        break;
+                    }
+                }
+            }
+        }));
+    }
+    drop(work_rx);
+    drop(result_tx);
+
+    for path in files {
+        if work_tx.send(path).is_err() {
+            break;
+        }
+    }
+    drop(work_tx);
+
+    // persist and forward each result as it arrives; each save takes
+    // CSV_LOCK and re-reads the CSV fresh so it merges with, rather than
+    // clobbers, any foreground edit made while the scan is in flight
+    for info in result_rx {
+        {
+            let _guard = CSV_LOCK.lock().unwrap();
+            let mut wallpapers_csv = WallpapersCsv::new();
+            wallpapers_csv.insert(info.filename(), info.clone());
+            wallpapers_csv.save();
+        }
+        let done = processed.fetch_add(1, Ordering::Relaxed) + 1;
+        if events
+            .send(ScanEvent::Progress {
+                processed: done,
+                total,
+            })
+            .is_err()
+            || events.send(ScanEvent::Loaded(info)).is_err()
+        {
+            break;
+        }
+    }
+
+    for handle in handles {
+        let _ = handle.join();
+    }
+}
+
+/// Decodes a single file, caches a thumbnail to disk, and computes its
+/// dimensions and face rectangles from the decoded buffer.
+fn scan_file(path: &Path) -> Option<WallInfo> {
+    let img = decode::open(path).ok()?;
+    // persist a downscaled preview so the UI can render the list cheaply
+    let _ = img
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .save(thumbnail_path(path));
+    // dimension + face detection are derived from the decoded buffer
+    WallInfo::from_image(path, &img)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_with_no_files_completes_without_touching_wallpapers_csv() {
+        // an empty file list never reaches the WallpapersCsv read-modify-write
+        // in run(), so this can't race or touch the real wallpaper info (run()
+        // does still create the thumbnail directory); it just exercises that the worker/collector threads start up and shut down
+        // cleanly with nothing to do
+        let scanner = Scanner::spawn(Vec::new(), 4);
+        let events: Vec<_> = scanner.events.iter().collect();
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+//! Applies the chosen wallpaper to the running compositor and remembers the
+//! last-applied selection so it can be re-set on login.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::decode;
+use crate::geometry::Geometry;
+use crate::wallpaper_dir;
+
+/// Supported wallpaper backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Swww,
+    Hyprpaper,
+    Feh,
+}
+
+impl Backend {
+    /// builds the command that sets `path` as the wallpaper
+    fn command(self, path: &Path) -> Command {
+        match self {
+            Self::Swww => {
+                let mut cmd = Command::new("swww");
+                cmd.arg("img").arg(path);
+                cmd
+            }
+            Self::Hyprpaper => {
+                let mut cmd = Command::new("hyprctl");
+                cmd.arg("hyprpaper")
+                    .arg("wallpaper")
+                    .arg(format!(",{}", path.display()));
+                cmd
+            }
+            Self::Feh => {
+                let mut cmd = Command::new("feh");
+                cmd.arg("--bg-fill").arg(path);
+                cmd
+            }
+        }
+    }
+}
+
+/// The last wallpaper applied, persisted so `--reapply` can restore it.
+///
+/// The full source path is stored (not just a filename) so wallpapers opened
+/// from an arbitrary `--paths` directory can be re-applied, along with the
+/// chosen crop geometry so the reapplied image matches what the user saw.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastApplied {
+    pub path: PathBuf,
+    pub geometry: Geometry,
+    pub backend: Backend,
+}
+
+/// path of the record file tracking the last-applied wallpaper
+fn record_path() -> PathBuf {
+    wallpaper_dir().join(".last_applied.json")
+}
+
+/// path the reapplied crop is rendered to before being set
+fn crop_cache_path() -> PathBuf {
+    wallpaper_dir().join(".last_applied_crop.png")
+}
+
+/// sets `path` as the wallpaper via `backend`, returning an error if the
+/// backend command cannot be run or exits unsuccessfully
+pub fn set(path: &Path, backend: Backend) -> std::io::Result<()> {
+    let status = backend.command(path).status()?;
+    if !status.success() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("wallpaper backend exited with {status}"),
+        ));
+    }
+    Ok(())
+}
+
+/// renders the `geometry` crop of `path`'s source image to the crop cache,
+/// returning the path of the rendered file ready to hand to [`set`]
+fn render_crop(path: &Path, geometry: &Geometry) -> std::io::Result<PathBuf> {
+    let cropped = decode::open(path)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?
+        .crop_imm(geometry.x, geometry.y, geometry.w, geometry.h);
+    let out = crop_cache_path();
+    cropped
+        .save(&out)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    Ok(out)
+}
+
+/// renders `path`'s `geometry` crop, applies it via `backend` and records the
+/// original (uncropped) `path` and `geometry` as the last-applied wallpaper
+pub fn apply(path: &Path, geometry: &Geometry, backend: Backend) -> std::io::Result<()> {
+    let cropped = render_crop(path, geometry)?;
+    set(&cropped, backend)?;
+    let record = LastApplied {
+        path: path.to_path_buf(),
+        geometry: geometry.clone(),
+        backend,
+    };
+    let json = serde_json::to_string_pretty(&record)?;
+    std::fs::write(record_path(), json)
+}
+
+/// reads the last-applied record, if any
+pub fn last_applied() -> Option<LastApplied> {
+    let json = std::fs::read_to_string(record_path()).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// re-applies the previously chosen wallpaper without opening the UI, e.g. on
+/// login via `--reapply`, restoring the stored crop
+pub fn reapply_last() -> std::io::Result<()> {
+    let record = last_applied().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "no wallpaper has been applied yet")
+    })?;
+
+    // render the stored crop from the original source, then set the result
+    let cropped = render_crop(&record.path, &record.geometry)?;
+    set(&cropped, record.backend)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swww_command_passes_the_path_directly() {
+        let cmd = Backend::Swww.command(Path::new("/tmp/wall.png"));
+        assert_eq!(cmd.get_program(), "swww");
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, ["img", "/tmp/wall.png"]);
+    }
+
+    #[test]
+    fn hyprpaper_command_prefixes_the_path_with_an_empty_monitor() {
+        let cmd = Backend::Hyprpaper.command(Path::new("/tmp/wall.png"));
+        assert_eq!(cmd.get_program(), "hyprctl");
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, ["hyprpaper", "wallpaper", ",/tmp/wall.png"]);
+    }
+
+    #[test]
+    fn feh_command_passes_the_path_directly() {
+        let cmd = Backend::Feh.command(Path::new("/tmp/wall.png"));
+        assert_eq!(cmd.get_program(), "feh");
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, ["--bg-fill", "/tmp/wall.png"]);
+    }
+}